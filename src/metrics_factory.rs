@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::{
     allocator::{
         returning_reference::{ReturnTarget, ReturningRef},
@@ -5,13 +7,17 @@ use crate::{
     },
     metrics::MetricsBehavior,
     pipeline::Sink,
-    types::Name,
+    types::{Dimension, Name},
 };
 
 pub struct MetricsFactory<TMetricsAllocator, TSink> {
     allocator: TMetricsAllocator,
     default_metrics_behavior: u32,
     sink: TSink,
+    // Applied to every TMetricsRef this factory creates, so scoped measurements and the
+    // aggregating sink's DimensionPosition automatically include them without every call
+    // site repeating `.dimension(...)`.
+    default_dimensions: BTreeMap<Name, Dimension>,
 }
 
 pub trait RecordingScope<TMetricsRef>: ReturnTarget<TMetricsRef>
@@ -86,6 +92,11 @@ where
     unsafe fn create_new_raw_metrics(&self, metrics_name: impl Into<Name>) -> TMetricsRef {
         let mut m = self.allocator.new_metrics(metrics_name);
         m.set_raw_behavior(self.default_metrics_behavior);
+        self.default_dimensions
+            .iter()
+            .for_each(|(name, dimension)| {
+                m.dimension(name.clone(), dimension.clone());
+            });
         m
     }
 }
@@ -102,15 +113,42 @@ where
         MetricsFactory::new_with_allocator(sink, behaviors, Default::default())
     }
 
+    // Attach dimensions (e.g. `host`, `region`, `build_version`) that should appear on every
+    // metric this factory produces, without having to repeat `.dimension(...)` at every
+    // record_scope() call site.
+    pub fn new_with_dimensions(sink: TSink, dimensions: BTreeMap<Name, Dimension>) -> Self {
+        MetricsFactory::new_with_allocator_and_dimensions(
+            sink,
+            &[MetricsBehavior::Default],
+            Default::default(),
+            dimensions,
+        )
+    }
+
     pub fn new_with_allocator(
         sink: TSink,
         behaviors: &[MetricsBehavior],
         allocator: TMetricsAllocator,
+    ) -> Self {
+        MetricsFactory::new_with_allocator_and_dimensions(
+            sink,
+            behaviors,
+            allocator,
+            BTreeMap::new(),
+        )
+    }
+
+    pub fn new_with_allocator_and_dimensions(
+        sink: TSink,
+        behaviors: &[MetricsBehavior],
+        allocator: TMetricsAllocator,
+        dimensions: BTreeMap<Name, Dimension>,
     ) -> Self {
         MetricsFactory {
             allocator,
             default_metrics_behavior: behaviors.iter().fold(0, |i, behavior| i | *behavior as u32),
             sink,
+            default_dimensions: dimensions,
         }
     }
 }
@@ -139,6 +177,8 @@ where
 
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeMap;
+
     use crate::{
         allocator::always_new_metrics_allocator::AlwaysNewMetricsAllocator,
         metrics::MetricsBehavior,
@@ -147,6 +187,7 @@ mod test {
             aggregating_sink::AggregatingSink, logging_sink::LoggingSink,
             serializing_sink::SerializingSink,
         },
+        types::{Dimension, Name},
     };
 
     use super::MetricsFactory;
@@ -197,4 +238,26 @@ mod test {
             metrics.dimension("some dimension", "a dim");
         }
     }
+
+    #[test_log::test]
+    fn metrics_factory_seeds_default_dimensions() {
+        let metrics_factory: MetricsFactory<AlwaysNewMetricsAllocator, AggregatingSink<_>> =
+            MetricsFactory::new_with_dimensions(
+                AggregatingSink::new(),
+                BTreeMap::from([(Name::from("host"), Dimension::from("localhost"))]),
+            );
+
+        let mut metrics = metrics_factory.record_scope("test");
+        metrics.dimension("some dimension", "a dim");
+
+        let dimensions: BTreeMap<Name, Dimension> =
+            metrics.dimensions.clone().into_iter().collect();
+        assert_eq!(
+            BTreeMap::from([
+                (Name::from("host"), Dimension::from("localhost")),
+                (Name::from("some dimension"), Dimension::from("a dim")),
+            ]),
+            dimensions,
+        );
+    }
 }
@@ -1,10 +1,15 @@
 use std::{
     cmp::{max, min},
     collections::{BTreeMap, HashMap},
+    hash::Hash,
+    mem,
+    ops::{Deref, DerefMut},
     sync::{
-        mpsc::{self, Receiver, SyncSender},
-        Mutex,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+        Arc, Mutex, RwLock,
     },
+    time::{Duration, SystemTime},
 };
 
 use crate::{
@@ -25,14 +30,63 @@ type DimensionPosition = BTreeMap<Name, Dimension>;
 // Within the dimension position there is a collection of named measurements; we'll store the aggregated view of these
 type MeasurementAggregationMap = HashMap<Name, Aggregation>;
 
-type Histogram = HashMap<i64, u64>;
+// Bucket (at 2 significant figures, see bucket_10_2_sigfigs) -> count. Public so that
+// callers can match on it from their own StatsFn implementation.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Histogram(HashMap<i64, u64>);
+
+impl Deref for Histogram {
+    type Target = HashMap<i64, u64>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl DerefMut for Histogram {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+impl FromIterator<(i64, u64)> for Histogram {
+    fn from_iter<T: IntoIterator<Item = (i64, u64)>>(iter: T) -> Self {
+        Histogram(iter.into_iter().collect())
+    }
+}
+
+impl Histogram {
+    /// Estimate the value at quantile `q` (clamped to `[0, 1]`) from the bucketed counts.
+    /// Buckets are walked in ascending numeric order, accumulating counts until the running
+    /// total reaches `ceil(q * total)`; the bucket where that happens is returned. Because
+    /// each bucket key already encodes its upper edge at 2 significant figures, this is a
+    /// conservative upper-bound estimate for that quantile. Returns `None` for an empty
+    /// histogram, and the smallest bucket key for `q == 0`.
+    pub fn quantile(&self, q: f64) -> Option<i64> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let q = q.clamp(0.0, 1.0);
+        let total: u64 = self.0.values().sum();
+        let target = (q * total as f64).ceil() as u64;
+
+        let mut buckets: Vec<&i64> = self.0.keys().collect();
+        buckets.sort();
+
+        let mut running = 0;
+        for bucket in buckets {
+            running += self.0[bucket];
+            if running >= target {
+                return Some(*bucket);
+            }
+        }
+        None
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-struct StatisticSet {
-    min: i64,
-    max: i64,
-    sum: i64,
-    count: u64,
+pub struct StatisticSet {
+    pub min: i64,
+    pub max: i64,
+    pub sum: i64,
+    pub count: u64,
 }
 impl Default for StatisticSet {
     fn default() -> Self {
@@ -61,20 +115,38 @@ impl HistogramAccumulate for Histogram {
     fn accumulate<T: Into<i64>>(&mut self, value: T) {
         let v = value.into();
         let b = bucket_10_2_sigfigs(v);
-        self.insert(b, self[&b] + 1);
+        self.entry(b).and_modify(|count| *count += 1).or_insert(1);
     }
 }
 
+// Public so that callers can match on it from their own StatsFn implementation.
 #[derive(Debug, PartialEq, Eq)]
-enum Aggregation {
+pub enum Aggregation {
     Histogram(Histogram),
     StatisticSet(StatisticSet),
 }
 
+// A single metric family, rolled up over one flush window and ready to hand to a
+// downstream sink (e.g. something that serializes and exports it).
+pub struct EventBatch {
+    pub metrics_name: Name,
+    pub dimension_position: DimensionPosition,
+    pub measurements: Vec<(Name, Measurement)>,
+    pub window_start: SystemTime,
+    pub window_end: SystemTime,
+}
+
+// Decides what a flushed Aggregation actually publishes, e.g. count/sum/min/max for a
+// StatisticSet or p50/p90/p99/count for a histogram, under whatever derived measurement
+// names the caller wants. Swap this to control cardinality and which summary statistics
+// reach the downstream sink; see `default_stats_fn` for the out-of-the-box behavior.
+pub type StatsFn = dyn Fn(&Name, &Aggregation) -> Vec<(Name, Measurement)> + Send + Sync;
+
 pub struct AggregatingSink<TMetricsRef> {
     map: Mutex<MetricsMap>,
     sender: SyncSender<TMetricsRef>,
     receiver: Receiver<TMetricsRef>,
+    stats_fn: Box<StatsFn>,
 }
 
 impl<TMetricsRef> Default for AggregatingSink<TMetricsRef>
@@ -91,18 +163,26 @@ where
     TMetricsRef: MetricsRef,
 {
     pub fn new_with_bound(bound: usize) -> Self {
+        Self::new_with_stats_fn(bound, default_stats_fn)
+    }
+
+    pub fn new() -> Self {
+        Self::new_with_bound(1024)
+    }
+
+    pub fn new_with_stats_fn(
+        bound: usize,
+        stats_fn: impl Fn(&Name, &Aggregation) -> Vec<(Name, Measurement)> + Send + Sync + 'static,
+    ) -> Self {
         let (sender, receiver) = mpsc::sync_channel(bound);
         AggregatingSink {
             map: Mutex::new(MetricsMap::default()),
             sender,
             receiver,
+            stats_fn: Box::new(stats_fn),
         }
     }
 
-    pub fn new() -> Self {
-        Self::new_with_bound(1024)
-    }
-
     // Consume a thread to process metrics aggregation (async support will come separately)
     pub fn run_aggregator_forever(&self) {
         while let Ok(sunk_metrics_ref) = self.receiver.recv() {
@@ -110,6 +190,77 @@ where
         }
     }
 
+    // Consume a thread to process metrics aggregation, flushing a rolled-up EventBatch to
+    // `downstream` on a fixed cadence instead of accumulating forever. Each window is
+    // independent: the map is swapped for a fresh one at flush time, so accept() calls
+    // landing during the swap are attributed to the new window rather than dropped.
+    pub fn run_aggregator_forever_with_interval<TDownstreamSink>(
+        &self,
+        interval: Duration,
+        downstream: TDownstreamSink,
+    ) where
+        TDownstreamSink: Sink<EventBatch>,
+    {
+        let mut window_start = SystemTime::now();
+        let mut next_flush = window_start + interval;
+        loop {
+            let now = SystemTime::now();
+            // Check the deadline independently of recv's result: under sustained ingest the
+            // channel can stay non-empty indefinitely, which would make recv_timeout behave
+            // like try_recv (always Ok, never Timeout) and starve the flush forever.
+            if now >= next_flush {
+                let window_end = now;
+                self.flush(window_start, window_end, &downstream);
+                window_start = window_end;
+                next_flush = window_start + interval;
+                continue;
+            }
+
+            let timeout = next_flush.duration_since(now).unwrap_or(Duration::ZERO);
+            match self.receiver.recv_timeout(timeout) {
+                Ok(sunk_metrics_ref) => {
+                    self.update_metrics_map(sunk_metrics_ref);
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+    }
+
+    fn flush<TDownstreamSink>(
+        &self,
+        window_start: SystemTime,
+        window_end: SystemTime,
+        downstream: &TDownstreamSink,
+    ) where
+        TDownstreamSink: Sink<EventBatch>,
+    {
+        let drained_map = {
+            let mut map = self.map.lock().expect("must be able to access metrics map");
+            mem::take(&mut *map)
+        };
+
+        drained_map
+            .into_iter()
+            .for_each(|(metrics_name, dimensioned_measurements_map)| {
+                dimensioned_measurements_map
+                    .into_iter()
+                    .for_each(|(dimension_position, measurements_map)| {
+                        let measurements = measurements_map
+                            .iter()
+                            .flat_map(|(name, aggregation)| (self.stats_fn)(name, aggregation))
+                            .collect();
+                        downstream.accept(EventBatch {
+                            metrics_name: metrics_name.clone(),
+                            dimension_position: dimension_position.clone(),
+                            measurements,
+                            window_start,
+                            window_end,
+                        });
+                    });
+            });
+    }
+
     fn update_metrics_map(&self, mut sunk_metrics: TMetricsRef)
     where
         TMetricsRef: MetricsRef,
@@ -212,6 +363,379 @@ impl<TMetricsRef> Sink<TMetricsRef> for AggregatingSink<TMetricsRef> {
     }
 }
 
+// A lock-free alternative to AggregatingSink. `accept` updates atomic accumulators directly
+// instead of hopping through a channel into a single aggregator thread, so ingest scales
+// across cores instead of serializing on one Mutex<MetricsMap>. Slots are located or
+// inserted under read/write locks; once a slot exists, updating it takes only read locks,
+// so concurrent updates do not block each other, but they do wait while a flush or a
+// first-time slot insert holds a write lock.
+type AtomicMeasurementAggregationMap = RwLock<HashMap<Name, AtomicAggregation>>;
+type AtomicDimensionedMeasurementsMap = RwLock<HashMap<DimensionPosition, Arc<AtomicMeasurementAggregationMap>>>;
+type AtomicMetricsMap = RwLock<HashMap<Name, Arc<AtomicDimensionedMeasurementsMap>>>;
+
+#[derive(Debug, Default)]
+struct AtomicStatisticSet {
+    min: AtomicI64,
+    max: AtomicI64,
+    sum: AtomicI64,
+    count: AtomicU64,
+}
+impl AtomicStatisticSet {
+    fn new() -> Self {
+        Self {
+            min: AtomicI64::new(i64::MAX),
+            max: AtomicI64::new(i64::MIN),
+            sum: AtomicI64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn accumulate<T: Into<i64>>(&self, value: T) {
+        let v: i64 = value.into();
+        fetch_min(&self.min, v);
+        fetch_max(&self.max, v);
+        self.sum.fetch_add(v, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> StatisticSet {
+        StatisticSet {
+            min: self.min.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+            sum: self.sum.load(Ordering::Relaxed),
+            count: self.count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn fetch_min(atomic: &AtomicI64, value: i64) {
+    let mut current = atomic.load(Ordering::Relaxed);
+    while value < current {
+        match atomic.compare_exchange_weak(current, value, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return,
+            Err(observed) => current = observed,
+        }
+    }
+}
+
+fn fetch_max(atomic: &AtomicI64, value: i64) {
+    let mut current = atomic.load(Ordering::Relaxed);
+    while value > current {
+        match atomic.compare_exchange_weak(current, value, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return,
+            Err(observed) => current = observed,
+        }
+    }
+}
+
+// A histogram backed by per-bucket atomics instead of a plain map, so concurrent
+// accumulation into existing buckets only takes a shared read lock; the first value in a
+// new bucket takes the write lock to insert it.
+#[derive(Debug, Default)]
+struct AtomicHistogram {
+    buckets: RwLock<HashMap<i64, AtomicU64>>,
+}
+impl AtomicHistogram {
+    fn accumulate<T: Into<i64>>(&self, value: T) {
+        let bucket = bucket_10_2_sigfigs(value.into());
+        if let Some(counter) = self
+            .buckets
+            .read()
+            .expect("must be able to read histogram buckets")
+            .get(&bucket)
+        {
+            counter.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.buckets
+            .write()
+            .expect("must be able to write histogram buckets")
+            .entry(bucket)
+            .or_insert_with(AtomicU64::default)
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Histogram {
+        self.buckets
+            .read()
+            .expect("must be able to read histogram buckets")
+            .iter()
+            .map(|(bucket, count)| (*bucket, count.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+enum AtomicAggregation {
+    Histogram(AtomicHistogram),
+    StatisticSet(AtomicStatisticSet),
+}
+impl AtomicAggregation {
+    fn snapshot(&self) -> Aggregation {
+        match self {
+            AtomicAggregation::Histogram(histogram) => Aggregation::Histogram(histogram.snapshot()),
+            AtomicAggregation::StatisticSet(stats) => Aggregation::StatisticSet(stats.snapshot()),
+        }
+    }
+}
+
+// Look up the Arc'd slot for `key`, taking only a short read lock on the common path where
+// it already exists; falls back to a write lock to insert it the first time it's seen.
+fn get_or_insert_slot<K, V>(map: &RwLock<HashMap<K, Arc<V>>>, key: &K) -> Arc<V>
+where
+    K: Clone + Eq + Hash,
+    V: Default,
+{
+    if let Some(existing) = map.read().expect("must be able to read slot map").get(key) {
+        return existing.clone();
+    }
+    map.write()
+        .expect("must be able to write slot map")
+        .entry(key.clone())
+        .or_insert_with(|| Arc::new(V::default()))
+        .clone()
+}
+
+pub struct AtomicAggregatingSink {
+    map: AtomicMetricsMap,
+    stats_fn: Box<StatsFn>,
+}
+
+impl Default for AtomicAggregatingSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtomicAggregatingSink {
+    pub fn new() -> Self {
+        Self::new_with_stats_fn(default_stats_fn)
+    }
+
+    pub fn new_with_stats_fn(
+        stats_fn: impl Fn(&Name, &Aggregation) -> Vec<(Name, Measurement)> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            map: RwLock::new(HashMap::new()),
+            stats_fn: Box::new(stats_fn),
+        }
+    }
+
+    // Resolve (or create) the top-level slot for `name` and run `f` while still holding
+    // `self.map`'s lock. flush() needs `self.map`'s write lock to swap the map out, so
+    // holding this lock for the whole operation (not just the lookup) guarantees flush can
+    // never discard a slot out from under an in-flight accept() that already resolved it --
+    // which would otherwise orphan the write: the accumulator would still be reachable via
+    // its Arc, but unreachable from self.map, so no future flush would ever read it. On the
+    // common path (the name already has a slot) this only takes a read lock, so concurrent
+    // accept() calls for existing names never block each other; only the rare first-touch-
+    // per-window insert, and the periodic flush, need exclusive access.
+    fn with_dimensioned_measurements_map<R>(
+        &self,
+        name: &Name,
+        f: impl FnOnce(&Arc<AtomicDimensionedMeasurementsMap>) -> R,
+    ) -> R {
+        let map = self.map.read().expect("must be able to read metrics map");
+        if let Some(existing) = map.get(name) {
+            return f(existing);
+        }
+        drop(map);
+
+        let mut map = self.map.write().expect("must be able to write metrics map");
+        let slot = map
+            .entry(name.clone())
+            .or_insert_with(|| Arc::new(AtomicDimensionedMeasurementsMap::default()));
+        f(slot)
+    }
+}
+
+impl AtomicAggregatingSink {
+    // Same tumbling-window cadence as AggregatingSink::run_aggregator_forever_with_interval,
+    // but there is no aggregator thread to drive receipt: this just drives the flush clock.
+    pub fn run_flush_forever<TDownstreamSink>(&self, interval: Duration, downstream: TDownstreamSink)
+    where
+        TDownstreamSink: Sink<EventBatch>,
+    {
+        let mut window_start = SystemTime::now();
+        loop {
+            std::thread::sleep(interval);
+            let window_end = SystemTime::now();
+            self.flush(window_start, window_end, &downstream);
+            window_start = window_end;
+        }
+    }
+
+    fn flush<TDownstreamSink>(
+        &self,
+        window_start: SystemTime,
+        window_end: SystemTime,
+        downstream: &TDownstreamSink,
+    ) where
+        TDownstreamSink: Sink<EventBatch>,
+    {
+        let drained_map = mem::take(
+            &mut *self
+                .map
+                .write()
+                .expect("must be able to access metrics map"),
+        );
+
+        drained_map
+            .into_iter()
+            .for_each(|(metrics_name, dimensioned_measurements_map)| {
+                let positions: Vec<_> = dimensioned_measurements_map
+                    .read()
+                    .expect("must be able to read dimensioned measurements map")
+                    .iter()
+                    .map(|(position, measurements_map)| (position.clone(), measurements_map.clone()))
+                    .collect();
+                positions
+                    .into_iter()
+                    .for_each(|(dimension_position, measurements_map)| {
+                        let measurements: Vec<(Name, Measurement)> = measurements_map
+                            .read()
+                            .expect("must be able to read measurements map")
+                            .iter()
+                            .flat_map(|(name, aggregation)| {
+                                (self.stats_fn)(name, &aggregation.snapshot())
+                            })
+                            .collect();
+                        downstream.accept(EventBatch {
+                            metrics_name: metrics_name.clone(),
+                            dimension_position,
+                            measurements,
+                            window_start,
+                            window_end,
+                        });
+                    });
+            });
+    }
+}
+
+impl<TMetricsRef> Sink<TMetricsRef> for AtomicAggregatingSink
+where
+    TMetricsRef: MetricsRef,
+{
+    fn accept(&self, mut metrics_ref: TMetricsRef) {
+        let metrics_name = metrics_ref.metrics_name.clone();
+        self.with_dimensioned_measurements_map(
+            &metrics_name,
+            |dimensioned_measurements_map| {
+                let position: DimensionPosition = metrics_ref.dimensions.drain().collect();
+                let measurements_map = get_or_insert_slot(dimensioned_measurements_map, &position);
+
+                metrics_ref
+                    .measurements
+                    .drain()
+                    .for_each(|(name, measurement)| match measurement {
+                        Measurement::Observation(observation) => {
+                            atomic_accumulate_statisticset(&measurements_map, name, observation);
+                        }
+                        Measurement::Distribution(distribution) => {
+                            atomic_accumulate_distribution(&measurements_map, name, distribution);
+                        }
+                    });
+            },
+        );
+    }
+}
+
+fn atomic_accumulate_statisticset(
+    measurements_map: &AtomicMeasurementAggregationMap,
+    name: Name,
+    observation: types::Observation,
+) {
+    if let Some(AtomicAggregation::StatisticSet(stats)) = measurements_map
+        .read()
+        .expect("must be able to read measurements map")
+        .get(&name)
+    {
+        stats.accumulate(observation);
+        return;
+    }
+    match measurements_map
+        .write()
+        .expect("must be able to write measurements map")
+        .entry(name)
+        .or_insert_with(|| AtomicAggregation::StatisticSet(AtomicStatisticSet::new()))
+    {
+        AtomicAggregation::StatisticSet(stats) => stats.accumulate(observation),
+        AtomicAggregation::Histogram(_h) => {
+            log::error!("conflicting measurement and distribution name")
+        }
+    }
+}
+
+fn atomic_accumulate_distribution(
+    measurements_map: &AtomicMeasurementAggregationMap,
+    name: Name,
+    distribution: types::Distribution,
+) {
+    if let Some(AtomicAggregation::Histogram(histogram)) = measurements_map
+        .read()
+        .expect("must be able to read measurements map")
+        .get(&name)
+    {
+        accumulate_distribution_into(histogram, distribution);
+        return;
+    }
+    match measurements_map
+        .write()
+        .expect("must be able to write measurements map")
+        .entry(name)
+        .or_insert_with(|| AtomicAggregation::Histogram(AtomicHistogram::default()))
+    {
+        AtomicAggregation::Histogram(histogram) => accumulate_distribution_into(histogram, distribution),
+        AtomicAggregation::StatisticSet(_s) => {
+            log::error!("conflicting measurement and distribution name")
+        }
+    }
+}
+
+fn accumulate_distribution_into(histogram: &AtomicHistogram, distribution: types::Distribution) {
+    match distribution {
+        types::Distribution::I64(i) => histogram.accumulate(i),
+        types::Distribution::I32(i) => histogram.accumulate(i),
+        types::Distribution::U64(i) => histogram.accumulate(i as i64),
+        types::Distribution::U32(i) => histogram.accumulate(i),
+        types::Distribution::Collection(collection) => {
+            collection.iter().for_each(|i| histogram.accumulate(*i as i64));
+        }
+    }
+}
+
+// The out-of-the-box StatsFn: publishes `<name>_min`/`_max`/`_sum`/`_count` for a
+// StatisticSet, and `<name>_p50`/`_p90`/`_p99`/`_count` for a Histogram.
+pub fn default_stats_fn(name: &Name, aggregation: &Aggregation) -> Vec<(Name, Measurement)> {
+    match aggregation {
+        Aggregation::StatisticSet(stats) => vec![
+            (format!("{name}_min").into(), Measurement::Observation(stats.min.into())),
+            (format!("{name}_max").into(), Measurement::Observation(stats.max.into())),
+            (format!("{name}_sum").into(), Measurement::Observation(stats.sum.into())),
+            (
+                format!("{name}_count").into(),
+                Measurement::Observation((stats.count as i64).into()),
+            ),
+        ],
+        Aggregation::Histogram(histogram) => {
+            let count: u64 = histogram.values().sum();
+            [0.5, 0.9, 0.99]
+                .into_iter()
+                .filter_map(|q| {
+                    histogram
+                        .quantile(q)
+                        .map(|v| (format!("{name}_p{}", (q * 100.0) as u32).into(), v))
+                })
+                .map(|(measurement_name, v)| (measurement_name, Measurement::Observation(v.into())))
+                .chain(std::iter::once((
+                    format!("{name}_count").into(),
+                    Measurement::Observation((count as i64).into()),
+                )))
+                .collect()
+        }
+    }
+}
+
 // Base 10 significant-figures bucketing
 fn bucket_10<const FIGURES: u32>(value: i64) -> i64 {
     // TODO: use i64.log10 when it's promoted to stable https://github.com/rust-lang/rust/issues/70887
@@ -232,17 +756,125 @@ fn bucket_10_2_sigfigs(value: i64) -> i64 {
 
 #[cfg(test)]
 mod test {
-    use std::collections::{BTreeMap, HashMap};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, SystemTime},
+    };
 
     use crate::{
         allocator::{always_new_metrics_allocator::AlwaysNewMetricsAllocator, MetricsAllocator},
         metrics::Metrics,
-        pipeline::aggregating_sink::{
-            bucket_10_2_sigfigs, AggregatingSink, Aggregation, StatisticSet,
+        pipeline::{
+            aggregating_sink::{
+                bucket_10_2_sigfigs, AggregatingSink, Aggregation, AtomicAggregatingSink,
+                EventBatch, Histogram, StatisticSet,
+            },
+            Sink,
         },
-        types::{Dimension, Name, Observation},
+        types::{Dimension, Measurement, Name, Observation},
     };
 
+    // A trivial Sink<EventBatch> that just remembers everything it was handed, so tests can
+    // drive flush()/run_*_forever_with_interval() without standing up a real downstream sink.
+    // Arc-backed so a handle can be kept in the test while a clone is moved into a worker
+    // thread running run_aggregator_forever_with_interval (which takes its sink by value).
+    #[derive(Default, Clone)]
+    struct CollectingSink {
+        batches: Arc<Mutex<Vec<EventBatch>>>,
+    }
+    impl Sink<EventBatch> for CollectingSink {
+        fn accept(&self, batch: EventBatch) {
+            self.batches.lock().unwrap().push(batch);
+        }
+    }
+
+    fn observation_value(measurement: Measurement) -> i64 {
+        match measurement {
+            Measurement::Observation(observation) => observation.into(),
+            Measurement::Distribution(_) => panic!("expected an observation"),
+        }
+    }
+
+    // A trivial Sink<EventBatch> that just remembers everything it was handed, Arc-backed so a
+    // handle can be kept on the test thread while a clone is moved into the flushing thread.
+    #[derive(Default, Clone)]
+    struct RaceCollectingSink {
+        batches: Arc<Mutex<Vec<EventBatch>>>,
+    }
+    impl Sink<EventBatch> for RaceCollectingSink {
+        fn accept(&self, batch: EventBatch) {
+            self.batches.lock().unwrap().push(batch);
+        }
+    }
+
+    // Drives a straggling accept() racing a flush's map swap: with_dimensioned_measurements_map
+    // holds self.map's lock for the whole accept() body, so a flush can never swap the top-level
+    // map out while a concurrent accept() still has its slot half-resolved -- which would
+    // otherwise orphan the write (reachable via its own Arc, but no longer reachable from
+    // self.map, so no future flush would ever see it). Assert the total "v_count" observed
+    // downstream across every flush equals the number of accepts, i.e. nothing was lost.
+    #[test_log::test]
+    fn test_atomic_aggregation_accept_does_not_race_flush() {
+        let sink = Arc::new(AtomicAggregatingSink::new());
+        let downstream = RaceCollectingSink::default();
+
+        const ACCEPTS: usize = 20_000;
+        let done = Arc::new(AtomicBool::new(false));
+
+        let producer = {
+            let sink = sink.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                for _ in 0..ACCEPTS {
+                    sink.accept(get_metrics("a", "dimension", "v", 1));
+                }
+                done.store(true, Ordering::SeqCst);
+            })
+        };
+
+        let flusher = {
+            let sink = sink.clone();
+            let downstream = downstream.clone();
+            std::thread::spawn(move || {
+                let mut window_start = SystemTime::now();
+                loop {
+                    let producer_done = done.load(Ordering::SeqCst);
+                    let window_end = SystemTime::now();
+                    sink.flush(window_start, window_end, &downstream);
+                    window_start = window_end;
+                    if producer_done {
+                        // One last flush to catch anything accepted between the check above
+                        // and the flush it guarded.
+                        sink.flush(window_start, SystemTime::now(), &downstream);
+                        return;
+                    }
+                }
+            })
+        };
+
+        producer.join().unwrap();
+        flusher.join().unwrap();
+
+        let total_count: i64 = downstream
+            .batches
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|batch| batch.measurements.iter())
+            .filter(|(name, _)| *name == Name::from("v_count"))
+            .map(|(_, measurement)| match measurement {
+                Measurement::Observation(observation) => i64::from(*observation),
+                Measurement::Distribution(_) => panic!("expected an observation"),
+            })
+            .sum();
+
+        assert_eq!(ACCEPTS as i64, total_count);
+    }
+
     #[test_log::test]
     fn test_bucket() {
         assert_eq!(1, bucket_10_2_sigfigs(1));
@@ -266,6 +898,18 @@ mod test {
         assert_eq!(-8900, bucket_10_2_sigfigs(-8801));
     }
 
+    #[test_log::test]
+    fn test_histogram_quantile() {
+        let empty = Histogram::default();
+        assert_eq!(None, empty.quantile(0.5));
+
+        let histogram: Histogram = [(1, 1), (2, 1), (3, 1), (4, 1)].into_iter().collect();
+        assert_eq!(Some(1), histogram.quantile(0.0));
+        assert_eq!(Some(2), histogram.quantile(0.5));
+        assert_eq!(Some(4), histogram.quantile(0.99));
+        assert_eq!(Some(4), histogram.quantile(1.0));
+    }
+
     #[test_log::test]
     fn test_aggregation() {
         let sink: AggregatingSink<Box<Metrics>> = AggregatingSink::new();
@@ -294,6 +938,132 @@ mod test {
         )
     }
 
+    #[test_log::test]
+    fn test_windowed_flush() {
+        let sink: AggregatingSink<Box<Metrics>> = AggregatingSink::new();
+
+        sink.update_metrics_map(get_metrics("a", "dimension", "v", 22));
+        sink.update_metrics_map(get_metrics("a", "dimension", "v", 20));
+
+        let downstream = CollectingSink::default();
+        let window_start = SystemTime::now();
+        let window_end = window_start + std::time::Duration::from_secs(1);
+        sink.flush(window_start, window_end, &downstream);
+
+        // The window was swapped out from under the live map, not just read from it.
+        assert!(sink.map.lock().unwrap().is_empty());
+
+        let batches = downstream.batches.lock().unwrap();
+        assert_eq!(1, batches.len());
+        let batch = &batches[0];
+        assert_eq!(Name::from("test"), batch.metrics_name);
+        assert_eq!(
+            BTreeMap::from([(Name::from("a"), Dimension::from("dimension"))]),
+            batch.dimension_position,
+        );
+        assert_eq!(window_start, batch.window_start);
+        assert_eq!(window_end, batch.window_end);
+
+        let measurements: HashMap<Name, i64> = batch
+            .measurements
+            .iter()
+            .map(|(name, measurement)| (name.clone(), observation_value(measurement.clone())))
+            .collect();
+        assert_eq!(Some(&20), measurements.get(&Name::from("v_min")));
+        assert_eq!(Some(&22), measurements.get(&Name::from("v_max")));
+        assert_eq!(Some(&42), measurements.get(&Name::from("v_sum")));
+        assert_eq!(Some(&2), measurements.get(&Name::from("v_count")));
+
+        // Flushing again with nothing accepted in between yields no further batches.
+        let downstream = CollectingSink::default();
+        sink.flush(window_end, window_end, &downstream);
+        assert!(downstream.batches.lock().unwrap().is_empty());
+    }
+
+    // AggregatingSink holds a bare mpsc::Receiver, which opts the whole struct out of Sync --
+    // even though only the single thread running the aggregator loop ever touches it; producer
+    // threads only ever reach the already-Sync SyncSender via accept(). This test is the only
+    // caller that needs to share a sink between a feeder thread and the aggregator thread, and
+    // it obeys that single-consumer invariant itself, so assert Sync just for this test.
+    struct AssertSync<T>(T);
+    unsafe impl<T> Sync for AssertSync<T> {}
+
+    #[test_log::test]
+    fn test_run_aggregator_forever_with_interval_flushes_on_schedule_under_load() {
+        let sink = Arc::new(AssertSync(AggregatingSink::<Box<Metrics>>::new_with_bound(
+            1_000_000,
+        )));
+        let downstream = CollectingSink::default();
+
+        // Pre-build a large backlog so the feeder thread can push with no per-message
+        // allocation in its hot loop: if the aggregator loop degenerates into
+        // recv_timeout(ZERO) (i.e. try_recv) once the window is overdue, a channel kept this
+        // saturated would starve the flush for the whole test instead of firing on schedule.
+        let pending: Vec<Box<Metrics>> = (0..500_000)
+            .map(|_| get_metrics("a", "dimension", "v", 1))
+            .collect();
+        let feeder = {
+            let sink = sink.clone();
+            std::thread::spawn(move || {
+                pending.into_iter().for_each(|m| sink.0.accept(m));
+            })
+        };
+
+        {
+            let sink = sink.clone();
+            let downstream = downstream.clone();
+            std::thread::spawn(move || {
+                sink.0
+                    .run_aggregator_forever_with_interval(Duration::from_millis(10), downstream);
+            });
+        }
+
+        feeder.join().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert!(
+            !downstream.batches.lock().unwrap().is_empty(),
+            "expected at least one flush on schedule despite sustained ingest"
+        );
+    }
+
+    #[test_log::test]
+    fn test_atomic_aggregation() {
+        let sink = AtomicAggregatingSink::new();
+
+        sink.accept(get_metrics("a", "dimension", "v", 22));
+        sink.accept(get_metrics("a", "dimension", "v", 20));
+
+        let downstream = CollectingSink::default();
+        let window_start = SystemTime::now();
+        let window_end = window_start + std::time::Duration::from_secs(1);
+        sink.flush(window_start, window_end, &downstream);
+
+        let batches = downstream.batches.lock().unwrap();
+        assert_eq!(1, batches.len());
+        let batch = &batches[0];
+        assert_eq!(Name::from("test"), batch.metrics_name);
+        assert_eq!(
+            BTreeMap::from([(Name::from("a"), Dimension::from("dimension"))]),
+            batch.dimension_position,
+        );
+
+        let measurements: HashMap<Name, i64> = batch
+            .measurements
+            .iter()
+            .map(|(name, measurement)| (name.clone(), observation_value(measurement.clone())))
+            .collect();
+        assert_eq!(Some(&20), measurements.get(&Name::from("v_min")));
+        assert_eq!(Some(&22), measurements.get(&Name::from("v_max")));
+        assert_eq!(Some(&42), measurements.get(&Name::from("v_sum")));
+        assert_eq!(Some(&2), measurements.get(&Name::from("v_count")));
+
+        // The drained map was swapped out, so a second flush reports nothing new.
+        let downstream = CollectingSink::default();
+        sink.flush(window_end, window_end, &downstream);
+        assert!(downstream.batches.lock().unwrap().is_empty());
+    }
+
     fn get_metrics(
         dimension_name: impl Into<Name>,
         dimension: impl Into<Dimension>,